@@ -0,0 +1,20 @@
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operator {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AST {
+    Num(f64),
+    Var(String),
+    BinOp(Box<AST>, Operator, Box<AST>),
+    /// A call to a user-defined function, e.g. `f(3, x)`. Resolved against a
+    /// [`crate::calculator::functions::FunctionRegistry`] at evaluation time
+    /// rather than folded during parsing, since the callee's definition (and
+    /// the values bound to its parameters) aren't known until then.
+    Call(String, Vec<AST>),
+}
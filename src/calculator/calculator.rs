@@ -1,31 +1,82 @@
+use std::fmt;
+
+use crate::calculator::config::Config;
+use crate::calculator::functions::FunctionRegistry;
 use crate::calculator::lexer::lex;
 use crate::calculator::{evaluator::{evaluate_infix, evaluate_postfix}};
-use crate::calculator::token::Token;
+use crate::calculator::scope::Scope;
+use crate::calculator::token::{Token, TokenKind};
 use crate::calculator::evaluator::solve_equation;
 use crate::calculator::parser::parse;
 
 #[derive(Debug, PartialEq)]
 pub enum CalculatorError {
     DivisionByZero,
+    NoRealSolution,
     ParseError,
-    UnexpectedToken,
+    UnexpectedToken { pos: usize, len: usize },
     InvalidExpression,
     MultipleVariables,
     EmptyExpression,
-    ExtraTokensDetected,
-    UnmatchedRightParenthesis,
-    UnmatchedLeftParenthesis,
+    ExtraTokensDetected { pos: usize, len: usize },
+    UnmatchedRightParenthesis { pos: usize, len: usize },
+    UnmatchedLeftParenthesis { pos: usize, len: usize },
+    UnknownBase,
+}
+
+impl CalculatorError {
+    /// The span (start offset, length) into the original input this error
+    /// points at, if any.
+    fn span(&self) -> Option<(usize, usize)> {
+        match self {
+            CalculatorError::UnexpectedToken { pos, len }
+            | CalculatorError::ExtraTokensDetected { pos, len }
+            | CalculatorError::UnmatchedRightParenthesis { pos, len }
+            | CalculatorError::UnmatchedLeftParenthesis { pos, len } => Some((*pos, *len)),
+            _ => None,
+        }
+    }
 }
-pub fn process_expression(input: &str) -> Result<String, CalculatorError> {
+
+impl fmt::Display for CalculatorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CalculatorError::DivisionByZero => write!(f, "division by zero"),
+            CalculatorError::NoRealSolution => write!(f, "equation has no real solution"),
+            CalculatorError::ParseError => write!(f, "failed to parse expression"),
+            CalculatorError::UnexpectedToken { pos, .. } => write!(f, "unexpected token at position {pos}"),
+            CalculatorError::InvalidExpression => write!(f, "invalid expression"),
+            CalculatorError::MultipleVariables => write!(f, "expression contains more than one variable"),
+            CalculatorError::EmptyExpression => write!(f, "expression is empty"),
+            CalculatorError::ExtraTokensDetected { pos, .. } => write!(f, "unexpected trailing input at position {pos}"),
+            CalculatorError::UnmatchedRightParenthesis { pos, .. } => write!(f, "unmatched ')' at position {pos}"),
+            CalculatorError::UnmatchedLeftParenthesis { pos, .. } => write!(f, "unmatched '(' at position {pos}"),
+            CalculatorError::UnknownBase => write!(f, "base must be between 2 and 36"),
+        }
+    }
+}
+
+impl std::error::Error for CalculatorError {}
+
+/// Renders `err` against the `input` it came from, underlining the offending
+/// substring with a caret run when the error carries a span.
+pub fn format_error(input: &str, err: &CalculatorError) -> String {
+    match err.span() {
+        Some((pos, len)) => format!("{err}\n{input}\n{}{}", " ".repeat(pos), "^".repeat(len.max(1))),
+        None => err.to_string(),
+    }
+}
+
+pub fn process_expression(input: &str, config: &Config) -> Result<String, CalculatorError> {
     let tokens = lex(input);
     if tokens.is_empty() {
         return Err(CalculatorError::EmptyExpression);
     }
-    let contains_equal = tokens.iter().any(|t| *t == Token::Equal);
+    let contains_equal = tokens.iter().any(|t| t.kind == TokenKind::Equal);
     let mut seen_variable = None;
 
     for token in &tokens {
-        if let Token::Variable(name) = token {
+        if let TokenKind::Variable(name) = &token.kind {
             match seen_variable {
                 None => seen_variable = Some(name.clone()),
                 Some(ref seen_name) if seen_name != name => {
@@ -38,29 +89,73 @@ pub fn process_expression(input: &str) -> Result<String, CalculatorError> {
 
     match seen_variable {
         Some(variable_name) if contains_equal => {
-            let result = solve_equation(&tokens)?;
-            Ok(format!("{}={}", variable_name, round_result(result)))
+            let roots = solve_equation(&tokens, config, &Scope::new(), &FunctionRegistry::new())?;
+            roots.into_iter()
+                .map(|root| Ok(format!("{}={}", variable_name, format_result(root, config)?)))
+                .collect::<Result<Vec<_>, _>>()
+                .map(|parts| parts.join(","))
         },
         _ => {
             if is_postfix_expression(&tokens) {
                 let result = evaluate_postfix(&tokens)?;
-                Ok(round_result(result).to_string())
+                format_result(result, config)
             } else {
-                let (ast, _) = parse(&tokens)?;
-                let result = evaluate_infix(&ast)?;
-                Ok(round_result(result).to_string())
+                let (ast, _) = parse(&tokens, config, &Scope::new(), &FunctionRegistry::new())?;
+                let result = evaluate_infix(&ast, &Scope::new(), &FunctionRegistry::new())?;
+                format_result(result, config)
             }
         }
     }
 }
 
-fn round_result(result: f64) -> f64 {
-    (result * 100000000.0).round() / 100000000.0
+fn round_result(result: f64, fix: usize) -> f64 {
+    let multiplier = 10f64.powi(fix as i32);
+    (result * multiplier).round() / multiplier
+}
+
+pub(crate) fn format_result(result: f64, config: &Config) -> Result<String, CalculatorError> {
+    let rounded = round_result(result, config.fix);
+    if config.base == 10 {
+        Ok(rounded.to_string())
+    } else {
+        render_in_base(rounded, config.base)
+    }
+}
+
+/// Renders an (expected-integral) result in an arbitrary radix via repeated
+/// division, producing digits `0-9a-z`.
+fn render_in_base(value: f64, base: usize) -> Result<String, CalculatorError> {
+    if !(2..=36).contains(&base) {
+        return Err(CalculatorError::UnknownBase);
+    }
+
+    let mut n = value.trunc() as i64;
+    if n == 0 {
+        return Ok("0".to_string());
+    }
+
+    let negative = n < 0;
+    if negative {
+        n = -n;
+    }
+
+    let mut digits = Vec::new();
+    while n > 0 {
+        let digit = (n % base as i64) as u32;
+        digits.push(std::char::from_digit(digit, base as u32).unwrap());
+        n /= base as i64;
+    }
+    if negative {
+        digits.push('-');
+    }
+
+    Ok(digits.iter().rev().collect())
 }
 
-fn is_postfix_expression(tokens: &[Token]) -> bool {
+pub(crate) fn is_postfix_expression(tokens: &[Token]) -> bool {
 
-    let contains_parentheses_or_equal = tokens.iter().any(|t| matches!(t, Token::LeftParenthesis | Token::RightParenthesis | Token::Equal));
+    let contains_parentheses_or_equal = tokens.iter()
+        .any(|t| matches!(t.kind, TokenKind::LeftParenthesis | TokenKind::RightParenthesis | TokenKind::Equal));
     if contains_parentheses_or_equal {
         return false;
     }
@@ -70,12 +165,12 @@ fn is_postfix_expression(tokens: &[Token]) -> bool {
     let mut operator_count = 0;
 
     for token in tokens {
-        match token {
-            Token::Number(_) => {
+        match &token.kind {
+            TokenKind::Number(_) => {
                 number_count += 1;
                 last_was_number = true;
             },
-            Token::Plus | Token::Minus | Token::Multiply | Token::Divide => {
+            TokenKind::Plus | TokenKind::Minus | TokenKind::Multiply | TokenKind::Divide => {
                 operator_count += 1;
                 if last_was_number && number_count - operator_count == 1 {
                     return true;
@@ -93,109 +188,213 @@ fn is_postfix_expression(tokens: &[Token]) -> bool {
 mod tests {
     use super::*;
 
+    fn process(input: &str) -> Result<String, CalculatorError> {
+        process_expression(input, &Config::default())
+    }
+
     #[test]
     fn test_basic_operations() {
-        assert_eq!(process_expression("1 + 1"), Ok("2".to_string()));
-        assert_eq!(process_expression("2 - 1"), Ok("1".to_string()));
-        assert_eq!(process_expression("2 * 3"), Ok("6".to_string()));
-        assert_eq!(process_expression("8 / 4"), Ok("2".to_string()));
+        assert_eq!(process("1 + 1"), Ok("2".to_string()));
+        assert_eq!(process("2 - 1"), Ok("1".to_string()));
+        assert_eq!(process("2 * 3"), Ok("6".to_string()));
+        assert_eq!(process("8 / 4"), Ok("2".to_string()));
     }
 
     #[test]
     fn test_complex_expressions() {
-        assert_eq!(process_expression("2 * (3 + 4)"), Ok("14".to_string()));
-        assert_eq!(process_expression("(2 + 3) * (4 - 1)"), Ok("15".to_string()));
+        assert_eq!(process("2 * (3 + 4)"), Ok("14".to_string()));
+        assert_eq!(process("(2 + 3) * (4 - 1)"), Ok("15".to_string()));
     }
 
     #[test]
     fn test_trigonometric_functions() {
-        assert_eq!(process_expression("cos(0)"), Ok("1".to_string()));
-        assert_eq!(process_expression("tan(pi/4)"), Ok("1".to_string()));
+        assert_eq!(process("cos(0)"), Ok("1".to_string()));
+        assert_eq!(process("tan(pi/4)"), Ok("1".to_string()));
     }
 
     #[test]
     fn test_logarithmic_functions() {
-        assert_eq!(process_expression("ln(e)"), Ok("1".to_string()));
-        assert_eq!(process_expression("log(100)"), Ok("2".to_string()));
+        assert_eq!(process("ln(e)"), Ok("1".to_string()));
+        assert_eq!(process("log(100)"), Ok("2".to_string()));
     }
 
     #[test]
     fn test_error_handling() {
-        assert!(process_expression("2 / 0").is_err());
-        assert!(process_expression("2 * (3 + 4").is_err());
-        assert!(process_expression("sin(90").is_err());
+        assert!(process("2 / 0").is_err());
+        assert!(process("2 * (3 + 4").is_err());
+        assert!(process("sin(90").is_err());
     }
 
     #[test]
     fn test_constants_and_variables() {
-        assert_eq!(process_expression("pi"), Ok("3.14159265".to_string()));
-        assert_eq!(process_expression("e"), Ok("2.71828183".to_string()));
-        assert_eq!(process_expression("2 * x + 1 = 3"), Ok("x=1".to_string()));
+        assert_eq!(process("pi"), Ok("3.14159265".to_string()));
+        assert_eq!(process("e"), Ok("2.71828183".to_string()));
+        assert_eq!(process("2 * x + 1 = 3"), Ok("x=1".to_string()));
     }
 
-
-
-
-
     #[test]
     fn evaluate_simple_expression() {
         let input = "(3+(4-1))*5";
-        let result = process_expression(input);
+        let result = process(input);
         assert_eq!(result, Ok("30".to_string()));
     }
 
     #[test]
     fn solve_linear_equation() {
         let input = "2 * x + 0.5 = 1";
-        let result = process_expression(input);
+        let result = process(input);
         assert_eq!(result, Ok("x=0.25".to_string()));
     }
 
     #[test]
     fn solve_equation_with_variables_on_both_sides() {
         let input = "2 * x + 1 = 2 * (1 - x)";
-        let result = process_expression(input);
+        let result = process(input);
         assert_eq!(result, Ok("x=0.25".to_string()));
     }
 
     #[test]
     fn test_log_base_10_of_10() {
         let input = "log(10)";
-        assert_eq!(process_expression(input), Ok("1".to_string()));
+        assert_eq!(process(input), Ok("1".to_string()));
 
         let input = "log10";
-        assert_eq!(process_expression(input), Ok("1".to_string()));
+        assert_eq!(process(input), Ok("1".to_string()));
     }
 
     #[test]
     fn test_log_base_100_of_10() {
         let input = "log100(10)";
-        assert_eq!(process_expression(input), Ok("0.5".to_string()));
+        assert_eq!(process(input), Ok("0.5".to_string()));
     }
 
     #[test]
     fn test_sin_of_pi() {
         let input = "sin(pi)";
-        assert_eq!(process_expression(input), Ok("0".to_string()));
+        assert_eq!(process(input), Ok("0".to_string()));
 
         let input = "sinpi";
-        assert_eq!(process_expression(input), Ok("0".to_string()));
+        assert_eq!(process(input), Ok("0".to_string()));
     }
 
     #[test]
     fn test_sin_of_1_5_pi() {
         let input = "sin(1.5pi)";
-        assert_eq!(process_expression(input), Ok("-1".to_string()));
+        assert_eq!(process(input), Ok("-1".to_string()));
 
         let input = "sin(1.5*pi)";
-        assert_eq!(process_expression(input), Ok("-1".to_string()));
+        assert_eq!(process(input), Ok("-1".to_string()));
     }
 
     #[test]
     fn test_postfix_expression() {
-        assert_eq!(process_expression("3 4 + 2 *"), Ok("14".to_string()));
-        assert_eq!(process_expression("10 2 8 * + 3 -"), Ok("23".to_string()));
+        assert_eq!(process("3 4 + 2 *"), Ok("14".to_string()));
+        assert_eq!(process("10 2 8 * + 3 -"), Ok("23".to_string()));
+    }
+
+    #[test]
+    fn test_degree_mode_trigonometry() {
+        let config = Config { radian_mode: false, ..Config::default() };
+        assert_eq!(process_expression("sin(90)", &config), Ok("1".to_string()));
+        assert_eq!(process_expression("cos(180)", &config), Ok("-1".to_string()));
+    }
+
+    #[test]
+    fn test_hexadecimal_and_binary_literals() {
+        assert_eq!(process("0x1A + 1"), Ok("27".to_string()));
+        assert_eq!(process("0b101 + 1"), Ok("6".to_string()));
+    }
+
+    #[test]
+    fn test_custom_base_output() {
+        let config = Config { base: 16, ..Config::default() };
+        assert_eq!(process_expression("255", &config), Ok("ff".to_string()));
+
+        let config = Config { base: 37, ..Config::default() };
+        assert_eq!(process_expression("1", &config), Err(CalculatorError::UnknownBase));
+    }
+
+    #[test]
+    fn test_base_below_two_is_an_error_not_a_panic() {
+        let config = Config { base: 0, ..Config::default() };
+        assert_eq!(process_expression("255", &config), Err(CalculatorError::UnknownBase));
+
+        let config = Config { base: 1, ..Config::default() };
+        assert_eq!(process_expression("255", &config), Err(CalculatorError::UnknownBase));
+    }
+
+    #[test]
+    fn test_error_position_and_caret() {
+        let err = process("2 * (3 + 4").unwrap_err();
+        assert_eq!(err, CalculatorError::UnmatchedLeftParenthesis { pos: 4, len: 1 });
+        assert_eq!(
+            format_error("2 * (3 + 4", &err),
+            "unmatched '(' at position 4\n2 * (3 + 4\n    ^"
+        );
+    }
+
+    #[test]
+    fn test_caret_underlines_a_single_character_token() {
+        let err = process("2 +++ 3").unwrap_err();
+        assert_eq!(err, CalculatorError::UnexpectedToken { pos: 3, len: 1 });
+        assert_eq!(
+            format_error("2 +++ 3", &err),
+            "unexpected token at position 3\n2 +++ 3\n   ^"
+        );
+    }
+
+    #[test]
+    fn test_caret_underlines_a_multi_character_token() {
+        // Classified as postfix after the leading "2 3 +", so the trailing
+        // variable is rejected by `evaluate_postfix` with its full span.
+        let err = process("2 3 + xyz").unwrap_err();
+        assert_eq!(err, CalculatorError::UnexpectedToken { pos: 6, len: 3 });
+        assert_eq!(
+            format_error("2 3 + xyz", &err),
+            "unexpected token at position 6\n2 3 + xyz\n      ^^^"
+        );
     }
 
+    #[test]
+    fn solve_quadratic_equation_with_two_distinct_roots() {
+        // x^2 - 3x + 2 = 0 factors as (x-1)(x-2).
+        assert_eq!(process("x*x-3*x+2=0"), Ok("x=2,x=1".to_string()));
+    }
+
+    #[test]
+    fn solve_quadratic_equation_with_a_repeated_root() {
+        // x^2 - 2x + 1 = (x-1)^2, a single repeated root collapses to one value.
+        assert_eq!(process("x*x-2*x+1=0"), Ok("x=1".to_string()));
+    }
 
+    #[test]
+    fn solve_quadratic_equation_with_no_real_solution() {
+        let err = process("x*x+1=0").unwrap_err();
+        assert_eq!(err, CalculatorError::NoRealSolution);
+    }
+
+    #[test]
+    fn solve_equation_rejects_degree_above_max() {
+        let err = process("x*x*x=1").unwrap_err();
+        assert_eq!(err, CalculatorError::InvalidExpression);
+    }
+
+    #[test]
+    fn test_exponentiation() {
+        assert_eq!(process("2^3"), Ok("8".to_string()));
+        assert_eq!(process("4 - 6 - 2"), Ok("-4".to_string()));
+    }
+
+    #[test]
+    fn test_exponentiation_is_right_associative() {
+        // 2^3^2 groups as 2^(3^2) = 2^9 = 512, not (2^3)^2 = 64.
+        assert_eq!(process("2^3^2"), Ok("512".to_string()));
+    }
+
+    #[test]
+    fn test_unary_minus_binds_looser_than_exponentiation() {
+        // -2^2 groups as -(2^2) = -4, not (-2)^2 = 4.
+        assert_eq!(process("-2^2"), Ok("-4".to_string()));
+        assert_eq!(process("-2^3^2"), Ok("-512".to_string()));
+    }
 }
@@ -0,0 +1,17 @@
+/// Output/interpretation settings threaded through `process_expression`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    /// Number of decimal places results are rounded to.
+    pub fix: usize,
+    /// When `false`, trig function arguments are interpreted in degrees
+    /// instead of radians.
+    pub radian_mode: bool,
+    /// Radix (2..=36) results are rendered in.
+    pub base: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config { fix: 8, radian_mode: true, base: 10 }
+    }
+}
@@ -1,73 +1,161 @@
 use crate::calculator::ast::{AST, Operator};
 use crate::calculator::calculator::CalculatorError;
-use crate::calculator::token::Token;
+use crate::calculator::config::Config;
+use crate::calculator::functions::FunctionRegistry;
+use crate::calculator::scope::Scope;
+use crate::calculator::token::{Token, TokenKind};
 use crate::calculator::parser::parse_expression;
 
-fn extract_coefficients_and_constants(ast: &AST) -> Result<(f64, f64), CalculatorError> {
+/// How many nested user-defined function calls `evaluate_infix` will follow
+/// before giving up on a runaway recursion (e.g. `f(x) = f(x)`).
+const MAX_CALL_DEPTH: usize = 64;
+
+/// Highest power of the variable we're willing to solve for. `extract_coefficients`
+/// rejects anything that would produce a higher-degree polynomial so the
+/// `Mul` convolution below always stays within a fixed-size vector.
+const MAX_DEGREE: usize = 2;
+
+/// Adds two coefficient vectors (index `i` holds the coefficient of `x^i`).
+fn poly_add(lhs: &[f64], rhs: &[f64]) -> Vec<f64> {
+    let len = lhs.len().max(rhs.len());
+    (0..len)
+        .map(|i| lhs.get(i).copied().unwrap_or(0.0) + rhs.get(i).copied().unwrap_or(0.0))
+        .collect()
+}
+
+/// Subtracts two coefficient vectors (`lhs - rhs`), term by term.
+fn poly_sub(lhs: &[f64], rhs: &[f64]) -> Vec<f64> {
+    let len = lhs.len().max(rhs.len());
+    (0..len)
+        .map(|i| lhs.get(i).copied().unwrap_or(0.0) - rhs.get(i).copied().unwrap_or(0.0))
+        .collect()
+}
+
+/// Multiplies two polynomials via convolution: a degree-m polynomial times a
+/// degree-n polynomial yields a degree m+n polynomial, `out[i+j] += a[i]*b[j]`.
+fn poly_mul(lhs: &[f64], rhs: &[f64]) -> Result<Vec<f64>, CalculatorError> {
+    let degree = lhs.len() + rhs.len() - 2;
+    if degree > MAX_DEGREE {
+        return Err(CalculatorError::InvalidExpression);
+    }
+    let mut out = vec![0.0; lhs.len() + rhs.len() - 1];
+    for (i, a) in lhs.iter().enumerate() {
+        for (j, b) in rhs.iter().enumerate() {
+            out[i + j] += a * b;
+        }
+    }
+    Ok(out)
+}
+
+/// Divides a polynomial by a constant. The divisor must have no `x` terms of
+/// its own (dividing by a polynomial isn't supported).
+fn poly_div_by_constant(lhs: &[f64], rhs: &[f64]) -> Result<Vec<f64>, CalculatorError> {
+    if rhs.iter().skip(1).any(|c| *c != 0.0) {
+        return Err(CalculatorError::InvalidExpression);
+    }
+    let divisor = rhs.first().copied().unwrap_or(0.0);
+    if divisor == 0.0 {
+        return Err(CalculatorError::DivisionByZero);
+    }
+    Ok(lhs.iter().map(|c| c / divisor).collect())
+}
+
+/// Raises a polynomial to a non-negative integer constant power by repeated
+/// convolution. The exponent itself must be a plain constant (no `x` term).
+fn poly_pow(lhs: &[f64], rhs: &[f64]) -> Result<Vec<f64>, CalculatorError> {
+    if rhs.iter().skip(1).any(|c| *c != 0.0) {
+        return Err(CalculatorError::InvalidExpression);
+    }
+    let exponent = rhs.first().copied().unwrap_or(0.0);
+    if exponent < 0.0 || exponent.fract() != 0.0 {
+        return Err(CalculatorError::InvalidExpression);
+    }
+
+    let mut result = vec![1.0];
+    for _ in 0..(exponent as u64) {
+        result = poly_mul(&result, lhs)?;
+    }
+    Ok(result)
+}
+
+/// Walks the AST, folding it into a coefficient vector indexed by power of
+/// the variable: index 0 is the constant term, index 1 is the `x`
+/// coefficient, index 2 is `x^2`, and so on up to [`MAX_DEGREE`].
+fn extract_coefficients(ast: &AST) -> Result<Vec<f64>, CalculatorError> {
     match ast {
-        AST::Num(n) => Ok((0.0, *n)),
-        AST::Var(_) => Ok((1.0, 0.0)),
+        AST::Num(n) => Ok(vec![*n]),
+        AST::Var(_) => Ok(vec![0.0, 1.0]),
+        AST::Call(_, _) => Err(CalculatorError::InvalidExpression),
         AST::BinOp(lhs, op, rhs) => {
-            let (lhs_coeff, lhs_const) = extract_coefficients_and_constants(lhs)?;
-            let (rhs_coeff, rhs_const) = extract_coefficients_and_constants(rhs)?;
+            let lhs_coeffs = extract_coefficients(lhs)?;
+            let rhs_coeffs = extract_coefficients(rhs)?;
 
             match op {
-                Operator::Add => Ok((lhs_coeff + rhs_coeff, lhs_const + rhs_const)),
-                Operator::Sub => Ok((lhs_coeff - rhs_coeff, lhs_const - rhs_const)),
-                Operator::Mul => {
-                    if lhs_coeff == 0.0 {
-                        Ok((rhs_coeff * lhs_const, rhs_const * lhs_const))
-                    } else if rhs_coeff == 0.0 {
-                        Ok((lhs_coeff * rhs_const, lhs_const * rhs_const))
-                    } else {
-                        Err(CalculatorError::InvalidExpression)
-                    }
-                },
-                Operator::Div => {
-                    if rhs_coeff != 0.0 {
-                        Err(CalculatorError::InvalidExpression)
-                    } else if rhs_const == 0.0 {
-                        Err(CalculatorError::DivisionByZero)
-                    } else {
-                        Ok((lhs_coeff / rhs_const, lhs_const / rhs_const))
-                    }
-                },
+                Operator::Add => Ok(poly_add(&lhs_coeffs, &rhs_coeffs)),
+                Operator::Sub => Ok(poly_sub(&lhs_coeffs, &rhs_coeffs)),
+                Operator::Mul => poly_mul(&lhs_coeffs, &rhs_coeffs),
+                Operator::Div => poly_div_by_constant(&lhs_coeffs, &rhs_coeffs),
+                Operator::Pow => poly_pow(&lhs_coeffs, &rhs_coeffs),
             }
         },
     }
 }
 
-pub(crate) fn solve_equation(tokens: &[Token]) -> Result<f64, CalculatorError> {
-    let equal_pos = tokens.iter().position(|t| *t == Token::Equal)
+pub(crate) fn solve_equation(tokens: &[Token], config: &Config, scope: &Scope, functions: &FunctionRegistry) -> Result<Vec<f64>, CalculatorError> {
+    let equal_pos = tokens.iter().position(|t| t.kind == TokenKind::Equal)
         .ok_or(CalculatorError::ParseError)?;
 
     let (left_tokens, right_tokens) = tokens.split_at(equal_pos);
     let right_tokens = &right_tokens[1..];
 
-    let (left_ast, _) = parse_expression(left_tokens)?;
-    let (right_ast, _) = parse_expression(right_tokens)?;
+    let (left_ast, _) = parse_expression(left_tokens, config, scope, functions)?;
+    let (right_ast, _) = parse_expression(right_tokens, config, scope, functions)?;
 
-    let (left_coefficient, left_constant) = extract_coefficients_and_constants(&left_ast)?;
-    let (right_coefficient, right_constant) = extract_coefficients_and_constants(&right_ast)?;
+    let left_coeffs = extract_coefficients(&left_ast)?;
+    let right_coeffs = extract_coefficients(&right_ast)?;
 
-    let a = left_coefficient - right_coefficient;
-    let b = right_constant - left_constant;
+    // Move everything to one side: c2*x^2 + c1*x + c0 = 0.
+    let mut coeffs = poly_sub(&left_coeffs, &right_coeffs);
+    coeffs.resize(MAX_DEGREE + 1, 0.0);
+    let c0 = coeffs[0];
+    let c1 = coeffs[1];
+    let c2 = coeffs[2];
 
-    if a == 0.0 {
-        return Err(CalculatorError::InvalidExpression);
+    if c2 == 0.0 {
+        if c1 == 0.0 {
+            return Err(CalculatorError::InvalidExpression);
+        }
+        return Ok(vec![-c0 / c1]);
     }
 
-    Ok(b / a)
+    let discriminant = c1 * c1 - 4.0 * c2 * c0;
+    if discriminant < 0.0 {
+        return Err(CalculatorError::NoRealSolution);
+    }
+
+    let sqrt_discriminant = discriminant.sqrt();
+    let root1 = (-c1 + sqrt_discriminant) / (2.0 * c2);
+    let root2 = (-c1 - sqrt_discriminant) / (2.0 * c2);
+
+    if root1 == root2 {
+        Ok(vec![root1])
+    } else {
+        Ok(vec![root1, root2])
+    }
 }
 
 
-pub(crate) fn evaluate_infix(ast: &AST) -> Result<f64, CalculatorError> {
+pub(crate) fn evaluate_infix(ast: &AST, scope: &Scope, functions: &FunctionRegistry) -> Result<f64, CalculatorError> {
+    evaluate_infix_at_depth(ast, scope, functions, 0)
+}
+
+fn evaluate_infix_at_depth(ast: &AST, scope: &Scope, functions: &FunctionRegistry, depth: usize) -> Result<f64, CalculatorError> {
     match ast {
         AST::Num(n) => Ok(*n),
-        AST::Var(_) => Err(CalculatorError::InvalidExpression),
+        AST::Var(name) => scope.get(name).copied().ok_or(CalculatorError::InvalidExpression),
         AST::BinOp(lhs, op, rhs) => {
-            let lhs_val = evaluate_infix(lhs)?;
-            let rhs_val = evaluate_infix(rhs)?;
+            let lhs_val = evaluate_infix_at_depth(lhs, scope, functions, depth)?;
+            let rhs_val = evaluate_infix_at_depth(rhs, scope, functions, depth)?;
             match op {
                 Operator::Add => Ok(lhs_val + rhs_val),
                 Operator::Sub => Ok(lhs_val - rhs_val),
@@ -79,7 +167,24 @@ pub(crate) fn evaluate_infix(ast: &AST) -> Result<f64, CalculatorError> {
                         Ok(lhs_val / rhs_val)
                     }
                 },
+                Operator::Pow => Ok(lhs_val.powf(rhs_val)),
+            }
+        },
+        AST::Call(name, args) => {
+            if depth >= MAX_CALL_DEPTH {
+                return Err(CalculatorError::InvalidExpression);
+            }
+            let def = functions.get(name).ok_or(CalculatorError::InvalidExpression)?;
+            if def.params.len() != args.len() {
+                return Err(CalculatorError::InvalidExpression);
+            }
+
+            let mut call_scope = Scope::new();
+            for (param, arg) in def.params.iter().zip(args) {
+                let value = evaluate_infix_at_depth(arg, scope, functions, depth)?;
+                call_scope.insert(param.clone(), value);
             }
+            evaluate_infix_at_depth(&def.body, &call_scope, functions, depth + 1)
         },
     }
 }
@@ -88,19 +193,19 @@ pub(crate) fn evaluate_postfix(tokens: &[Token]) -> Result<f64, CalculatorError>
     let mut stack: Vec<f64> = Vec::new();
 
     for token in tokens {
-        match token {
-            Token::Number(n) => stack.push(*n),
-            Token::Plus | Token::Minus | Token::Multiply | Token::Divide => {
+        match &token.kind {
+            TokenKind::Number(n) => stack.push(*n),
+            TokenKind::Plus | TokenKind::Minus | TokenKind::Multiply | TokenKind::Divide => {
                 if stack.len() < 2 {
                     return Err(CalculatorError::InvalidExpression);
                 }
                 let rhs = stack.pop().unwrap();
                 let lhs = stack.pop().unwrap();
-                let result = match token {
-                    Token::Plus => lhs + rhs,
-                    Token::Minus => lhs - rhs,
-                    Token::Multiply => lhs * rhs,
-                    Token::Divide => {
+                let result = match &token.kind {
+                    TokenKind::Plus => lhs + rhs,
+                    TokenKind::Minus => lhs - rhs,
+                    TokenKind::Multiply => lhs * rhs,
+                    TokenKind::Divide => {
                         if rhs == 0.0 {
                             return Err(CalculatorError::DivisionByZero);
                         }
@@ -110,7 +215,7 @@ pub(crate) fn evaluate_postfix(tokens: &[Token]) -> Result<f64, CalculatorError>
                 };
                 stack.push(result);
             },
-            _ => return Err(CalculatorError::UnexpectedToken),
+            _ => return Err(CalculatorError::UnexpectedToken { pos: token.pos, len: token.len }),
         }
     }
 
@@ -0,0 +1,14 @@
+use std::collections::HashMap;
+
+use crate::calculator::ast::AST;
+
+/// A user-defined function's parameter names and body, as registered by a
+/// `name(params) = body` definition line, e.g. `f(x) = x*x + 1`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct FunctionDef {
+    pub params: Vec<String>,
+    pub body: AST,
+}
+
+/// Maps a user-defined function's name to its definition.
+pub(crate) type FunctionRegistry = HashMap<String, FunctionDef>;
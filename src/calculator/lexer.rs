@@ -0,0 +1,99 @@
+use crate::calculator::token::{Token, TokenKind};
+
+const FUNCTION_NAMES: [&str; 5] = ["sin", "cos", "tan", "ln", "log"];
+
+pub(crate) fn lex(input: &str) -> Vec<Token> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '0' && matches!(chars.get(i + 1), Some('x') | Some('b')) {
+            let radix: u32 = if chars[i + 1] == 'x' { 16 } else { 2 };
+            let mut end = i + 2;
+            while end < chars.len() && chars[end].is_digit(radix) {
+                end += 1;
+            }
+            let literal: String = chars[i + 2..end].iter().collect();
+            if let Ok(n) = i64::from_str_radix(&literal, radix) {
+                tokens.push(Token { kind: TokenKind::Number(n as f64), pos: start, len: end - start });
+            }
+            i = end;
+        } else if c.is_ascii_digit() || c == '.' {
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            let literal: String = chars[start..i].iter().collect();
+            if let Ok(n) = literal.parse::<f64>() {
+                tokens.push(Token { kind: TokenKind::Number(n), pos: start, len: i - start });
+            }
+        } else if c.is_alphabetic() {
+            while i < chars.len() && chars[i].is_alphanumeric() {
+                i += 1;
+            }
+            let word: String = chars[start..i].iter().collect();
+            push_identifier(&word, start, &mut tokens);
+        } else {
+            let kind = match c {
+                '+' => Some(TokenKind::Plus),
+                '-' => Some(TokenKind::Minus),
+                '*' => Some(TokenKind::Multiply),
+                '/' => Some(TokenKind::Divide),
+                '^' => Some(TokenKind::Caret),
+                '(' => Some(TokenKind::LeftParenthesis),
+                ')' => Some(TokenKind::RightParenthesis),
+                '=' => Some(TokenKind::Equal),
+                ',' => Some(TokenKind::Comma),
+                _ => None,
+            };
+            if let Some(kind) = kind {
+                tokens.push(Token { kind, pos: start, len: 1 });
+            }
+            i += 1;
+        }
+    }
+
+    tokens
+}
+
+/// Splits a word like `sinpi` or `log100` into a known function prefix and
+/// whatever trails it, so the parser can decide how to interpret the
+/// remainder (a fused constant, a log base, or nothing at all).
+fn split_function_prefix(word: &str) -> Option<(&'static str, &str)> {
+    FUNCTION_NAMES.iter()
+        .find(|name| word.starts_with(*name))
+        .map(|name| (*name, &word[name.len()..]))
+}
+
+fn push_identifier(word: &str, pos: usize, tokens: &mut Vec<Token>) {
+    if let Some((name, suffix)) = split_function_prefix(word) {
+        if suffix.is_empty() || suffix.chars().all(|c| c.is_ascii_digit()) {
+            tokens.push(Token { kind: TokenKind::Function(format!("{name}{suffix}")), pos, len: word.len() });
+            return;
+        }
+        if let Some(value) = constant_value(suffix) {
+            tokens.push(Token { kind: TokenKind::Function(name.to_string()), pos, len: name.len() });
+            tokens.push(Token { kind: TokenKind::Number(value), pos: pos + name.len(), len: suffix.len() });
+            return;
+        }
+    }
+
+    if let Some(value) = constant_value(word) {
+        tokens.push(Token { kind: TokenKind::Number(value), pos, len: word.len() });
+    } else {
+        tokens.push(Token { kind: TokenKind::Variable(word.to_string()), pos, len: word.chars().count() });
+    }
+}
+
+fn constant_value(name: &str) -> Option<f64> {
+    match name {
+        "pi" => Some(std::f64::consts::PI),
+        "e" => Some(std::f64::consts::E),
+        _ => None,
+    }
+}
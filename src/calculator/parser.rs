@@ -0,0 +1,260 @@
+use crate::calculator::ast::{AST, Operator};
+use crate::calculator::calculator::CalculatorError;
+use crate::calculator::config::Config;
+use crate::calculator::evaluator::evaluate_infix;
+use crate::calculator::functions::FunctionRegistry;
+use crate::calculator::scope::Scope;
+use crate::calculator::token::{Token, TokenKind};
+
+pub(crate) fn parse<'a>(tokens: &'a [Token], config: &Config, scope: &Scope, functions: &FunctionRegistry) -> Result<(AST, &'a [Token]), CalculatorError> {
+    let (ast, rest) = parse_expression(tokens, config, scope, functions)?;
+    if let Some(token) = rest.first() {
+        return Err(CalculatorError::ExtraTokensDetected { pos: token.pos, len: token.len });
+    }
+    Ok((ast, rest))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Associativity {
+    Left,
+    Right,
+}
+
+/// A binary operator's entry in the precedence table: how tightly it binds
+/// and which side it groups on when its precedence ties with a neighbour.
+#[derive(Debug, Clone, Copy)]
+struct OperatorEntry {
+    precedence: u8,
+    assoc: Associativity,
+    operator: Operator,
+}
+
+const IMPLICIT_MULTIPLY: OperatorEntry = OperatorEntry {
+    precedence: 2,
+    assoc: Associativity::Left,
+    operator: Operator::Mul,
+};
+
+fn operator_entry(kind: &TokenKind) -> Option<OperatorEntry> {
+    use Associativity::{Left, Right};
+    match kind {
+        TokenKind::Plus => Some(OperatorEntry { precedence: 1, assoc: Left, operator: Operator::Add }),
+        TokenKind::Minus => Some(OperatorEntry { precedence: 1, assoc: Left, operator: Operator::Sub }),
+        TokenKind::Multiply => Some(OperatorEntry { precedence: 2, assoc: Left, operator: Operator::Mul }),
+        TokenKind::Divide => Some(OperatorEntry { precedence: 2, assoc: Left, operator: Operator::Div }),
+        TokenKind::Caret => Some(OperatorEntry { precedence: 3, assoc: Right, operator: Operator::Pow }),
+        _ => None,
+    }
+}
+
+/// Starts an operand: a bare `Number`/`Variable`/`Function`/`(` with no
+/// explicit operator before it implies multiplication, e.g. `1.5pi`.
+fn starts_operand(kind: &TokenKind) -> bool {
+    matches!(kind, TokenKind::Number(_) | TokenKind::Variable(_) | TokenKind::Function(_) | TokenKind::LeftParenthesis)
+}
+
+fn apply_operator(output: &mut Vec<AST>, operator: Operator) -> Result<(), CalculatorError> {
+    let rhs = output.pop().ok_or(CalculatorError::InvalidExpression)?;
+    let lhs = output.pop().ok_or(CalculatorError::InvalidExpression)?;
+    output.push(AST::BinOp(Box::new(lhs), operator, Box::new(rhs)));
+    Ok(())
+}
+
+/// Shunting-yard over the operator table above: each new operator pops and
+/// applies anything already on the stack that binds at least as tightly
+/// (strictly tighter, or equal precedence with left-associativity) before
+/// being pushed itself, so `2^3^2` groups right and `4 - 6 - 2` groups left.
+pub(crate) fn parse_expression<'a>(tokens: &'a [Token], config: &Config, scope: &Scope, functions: &FunctionRegistry) -> Result<(AST, &'a [Token]), CalculatorError> {
+    let (first_operand, mut rest) = parse_operand(tokens, config, scope, functions)?;
+    let mut output = vec![first_operand];
+    let mut operators: Vec<OperatorEntry> = Vec::new();
+
+    loop {
+        let next_kind = rest.first().map(|t| &t.kind);
+        let (entry, consumed_token) = match next_kind.and_then(operator_entry) {
+            Some(entry) => (Some(entry), true),
+            None => match next_kind {
+                Some(kind) if starts_operand(kind) => (Some(IMPLICIT_MULTIPLY), false),
+                _ => (None, false),
+            },
+        };
+
+        let Some(entry) = entry else { break };
+
+        while let Some(top) = operators.last() {
+            let should_reduce = top.precedence > entry.precedence
+                || (top.precedence == entry.precedence && entry.assoc == Associativity::Left);
+            if !should_reduce {
+                break;
+            }
+            apply_operator(&mut output, operators.pop().unwrap().operator)?;
+        }
+        operators.push(entry);
+
+        if consumed_token {
+            rest = &rest[1..];
+        }
+
+        let (operand, next) = parse_operand(rest, config, scope, functions)?;
+        output.push(operand);
+        rest = next;
+    }
+
+    while let Some(entry) = operators.pop() {
+        apply_operator(&mut output, entry.operator)?;
+    }
+
+    let result = output.pop().ok_or(CalculatorError::InvalidExpression)?;
+    Ok((result, rest))
+}
+
+fn parse_operand<'a>(tokens: &'a [Token], config: &Config, scope: &Scope, functions: &FunctionRegistry) -> Result<(AST, &'a [Token]), CalculatorError> {
+    match tokens.first() {
+        Some(Token { kind: TokenKind::Number(n), .. }) => Ok((AST::Num(*n), &tokens[1..])),
+        Some(Token { kind: TokenKind::Variable(name), .. }) => {
+            if matches!(tokens.get(1).map(|t| &t.kind), Some(TokenKind::LeftParenthesis)) {
+                parse_call(&name.clone(), &tokens[1..], config, scope, functions)
+            } else {
+                Ok((AST::Var(name.clone()), &tokens[1..]))
+            }
+        },
+        Some(Token { kind: TokenKind::Minus, .. }) => {
+            let (operand, rest) = parse_power(&tokens[1..], config, scope, functions)?;
+            Ok((AST::BinOp(Box::new(AST::Num(0.0)), Operator::Sub, Box::new(operand)), rest))
+        },
+        Some(Token { kind: TokenKind::Function(name), .. }) => {
+            parse_function_call(&name.clone(), &tokens[1..], config, scope, functions)
+        },
+        Some(Token { kind: TokenKind::LeftParenthesis, pos, len }) => {
+            let (open_pos, open_len) = (*pos, *len);
+            let (inner, rest) = parse_expression(&tokens[1..], config, scope, functions)?;
+            match rest.first() {
+                Some(Token { kind: TokenKind::RightParenthesis, .. }) => Ok((inner, &rest[1..])),
+                _ => Err(CalculatorError::UnmatchedLeftParenthesis { pos: open_pos, len: open_len }),
+            }
+        },
+        Some(Token { kind: TokenKind::RightParenthesis, pos, len }) => {
+            Err(CalculatorError::UnmatchedRightParenthesis { pos: *pos, len: *len })
+        },
+        Some(token) => Err(CalculatorError::UnexpectedToken { pos: token.pos, len: token.len }),
+        None => Err(CalculatorError::UnexpectedToken { pos: 0, len: 0 }),
+    }
+}
+
+/// Parses an operand followed by an optional right-associative `^` chain, so
+/// a leading unary minus binds looser than exponentiation: `-2^2` parses as
+/// `-(2^2)` rather than `(-2)^2`, matching conventional precedence.
+fn parse_power<'a>(tokens: &'a [Token], config: &Config, scope: &Scope, functions: &FunctionRegistry) -> Result<(AST, &'a [Token]), CalculatorError> {
+    let (base, rest) = parse_operand(tokens, config, scope, functions)?;
+    match rest.first() {
+        Some(Token { kind: TokenKind::Caret, .. }) => {
+            let (exponent, after) = parse_power(&rest[1..], config, scope, functions)?;
+            Ok((AST::BinOp(Box::new(base), Operator::Pow, Box::new(exponent)), after))
+        },
+        _ => Ok((base, rest)),
+    }
+}
+
+/// Parses a user-defined function call `name(arg1, arg2, ...)`. `rest` starts
+/// at the opening `(`; arguments are full expressions separated by `Comma`,
+/// folded into an `AST::Call` node rather than evaluated here, since the
+/// callee's definition is only known at evaluation time.
+fn parse_call<'a>(name: &str, rest: &'a [Token], config: &Config, scope: &Scope, functions: &FunctionRegistry) -> Result<(AST, &'a [Token]), CalculatorError> {
+    let (open_pos, open_len) = (rest[0].pos, rest[0].len);
+    let mut args = Vec::new();
+    let mut remaining = &rest[1..];
+
+    if !matches!(remaining.first().map(|t| &t.kind), Some(TokenKind::RightParenthesis)) {
+        loop {
+            let (arg, after_arg) = parse_expression(remaining, config, scope, functions)?;
+            args.push(arg);
+            match after_arg.first() {
+                Some(Token { kind: TokenKind::Comma, .. }) => remaining = &after_arg[1..],
+                _ => {
+                    remaining = after_arg;
+                    break;
+                },
+            }
+        }
+    }
+
+    match remaining.first() {
+        Some(Token { kind: TokenKind::RightParenthesis, .. }) => Ok((AST::Call(name.to_string(), args), &remaining[1..])),
+        _ => Err(CalculatorError::UnmatchedLeftParenthesis { pos: open_pos, len: open_len }),
+    }
+}
+
+/// Unary functions (`sin`, `log100`, ...) are folded into a constant
+/// immediately: a `logN` token followed by `(arg)` treats `N` as the base and
+/// the parenthesized expression as the argument, while a bare `logN` (no
+/// parentheses) treats `N` itself as the argument against the default base.
+/// A fused constant suffix (`sinpi`) was already split by the lexer into a
+/// `Function` token followed by a `Number`, so it's handled the same way as
+/// an explicit argument.
+fn parse_function_call<'a>(raw_name: &str, rest: &'a [Token], config: &Config, scope: &Scope, functions: &FunctionRegistry) -> Result<(AST, &'a [Token]), CalculatorError> {
+    let (name, digits) = split_log_digits(raw_name);
+
+    if matches!(rest.first().map(|t| &t.kind), Some(TokenKind::LeftParenthesis)) {
+        let (open_pos, open_len) = (rest[0].pos, rest[0].len);
+        let (arg, after_arg) = parse_expression(&rest[1..], config, scope, functions)?;
+        let after_close = match after_arg.first() {
+            Some(Token { kind: TokenKind::RightParenthesis, .. }) => &after_arg[1..],
+            _ => return Err(CalculatorError::UnmatchedLeftParenthesis { pos: open_pos, len: open_len }),
+        };
+        let base = digits.and_then(|d| d.parse::<f64>().ok());
+        let value = apply_function(name, base, &arg, config, scope, functions)?;
+        return Ok((AST::Num(value), after_close));
+    }
+
+    if let Some(digits) = digits {
+        let arg = AST::Num(digits.parse().map_err(|_| CalculatorError::InvalidExpression)?);
+        let value = apply_function(name, None, &arg, config, scope, functions)?;
+        return Ok((AST::Num(value), rest));
+    }
+
+    let (arg, after_arg) = parse_operand(rest, config, scope, functions)?;
+    let value = apply_function(name, None, &arg, config, scope, functions)?;
+    Ok((AST::Num(value), after_arg))
+}
+
+fn split_log_digits(raw_name: &str) -> (&str, Option<&str>) {
+    match raw_name.strip_prefix("log") {
+        Some(digits) if !digits.is_empty() => ("log", Some(digits)),
+        _ => (raw_name, None),
+    }
+}
+
+/// Converts a trig argument from degrees to radians unless the caller has
+/// already opted into radian mode.
+fn to_radians(x: f64, config: &Config) -> f64 {
+    if config.radian_mode {
+        x
+    } else {
+        x * std::f64::consts::PI / 180.0
+    }
+}
+
+fn apply_function(name: &str, base: Option<f64>, arg: &AST, config: &Config, scope: &Scope, functions: &FunctionRegistry) -> Result<f64, CalculatorError> {
+    let x = evaluate_infix(arg, scope, functions)?;
+    match name {
+        "sin" => Ok(to_radians(x, config).sin()),
+        "cos" => Ok(to_radians(x, config).cos()),
+        "tan" => Ok(to_radians(x, config).tan()),
+        "ln" => {
+            if x <= 0.0 {
+                Err(CalculatorError::InvalidExpression)
+            } else {
+                Ok(x.ln())
+            }
+        },
+        "log" => {
+            let base = base.unwrap_or(10.0);
+            if x <= 0.0 || base <= 0.0 || base == 1.0 {
+                Err(CalculatorError::InvalidExpression)
+            } else {
+                Ok(x.log(base))
+            }
+        },
+        _ => Err(CalculatorError::InvalidExpression),
+    }
+}
@@ -0,0 +1,299 @@
+use std::io::{self, BufRead, Write};
+
+use crate::calculator::ast::AST;
+use crate::calculator::calculator::{format_error, format_result, is_postfix_expression, process_expression, CalculatorError};
+use crate::calculator::config::Config;
+use crate::calculator::evaluator::{evaluate_infix, evaluate_postfix, solve_equation};
+use crate::calculator::functions::{FunctionDef, FunctionRegistry};
+use crate::calculator::lexer::lex;
+use crate::calculator::parser::parse;
+use crate::calculator::scope::Scope;
+use crate::calculator::token::{Token, TokenKind};
+
+/// Interactive front end over [`process_expression`] that remembers variable
+/// bindings and user-defined functions across lines. A line of the form
+/// `name = expr` is a variable assignment rather than an equation to solve:
+/// `expr` is evaluated against the current bindings and stored under `name`
+/// (and under `ans`). A line of the form `name(params) = body` defines a
+/// function instead of binding a variable, so later calls like `name(3)`
+/// evaluate `body` with `params` bound to the call's arguments.
+pub struct Repl {
+    scope: Scope,
+    functions: FunctionRegistry,
+    history: Vec<String>,
+}
+
+impl Default for Repl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Repl {
+    pub fn new() -> Self {
+        Repl { scope: Scope::new(), functions: FunctionRegistry::new(), history: Vec::new() }
+    }
+
+    /// The value last bound to `name` (including the implicit `ans`), if any.
+    pub fn binding(&self, name: &str) -> Option<f64> {
+        self.scope.get(name).copied()
+    }
+
+    /// The parameter count of a previously defined function, if any.
+    pub fn arity(&self, name: &str) -> Option<usize> {
+        self.functions.get(name).map(|def| def.params.len())
+    }
+
+    /// Every line successfully evaluated so far, oldest first.
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
+
+    /// Evaluates one line of input, updating bindings/definitions as a side
+    /// effect. Equations (`2*x+1=3`) are formatted via [`process_expression`]
+    /// and postfix expressions via the same postfix evaluator it uses, so
+    /// their output is unchanged, but `ans` is still updated
+    /// (to an equation's last root, or the postfix result) so it always
+    /// reflects the most recent line; everything else is parsed and
+    /// evaluated against the REPL's own scope and function registry so
+    /// bound variables and user-defined calls resolve.
+    pub fn eval_line(&mut self, line: &str, config: &Config) -> Result<String, CalculatorError> {
+        let tokens = lex(line);
+        if tokens.is_empty() {
+            return Err(CalculatorError::EmptyExpression);
+        }
+
+        if let Some((name, value_tokens)) = split_assignment(&tokens) {
+            let (ast, rest) = parse(value_tokens, config, &self.scope, &self.functions)?;
+            if let Some(token) = rest.first() {
+                return Err(CalculatorError::ExtraTokensDetected { pos: token.pos, len: token.len });
+            }
+            let value = evaluate_infix(&ast, &self.scope, &self.functions)?;
+            self.scope.insert(name.clone(), value);
+            self.scope.insert("ans".to_string(), value);
+            self.history.push(line.to_string());
+            return Ok(format!("{}={}", name, format_result(value, config)?));
+        }
+
+        if let Some(equal_pos) = tokens.iter().position(|t| t.kind == TokenKind::Equal) {
+            let (head, after_equal) = tokens.split_at(equal_pos);
+            if let Some((name, params)) = definition_head(head, config, &self.scope, &self.functions) {
+                let body_tokens = &after_equal[1..];
+                let (body, rest) = parse(body_tokens, config, &self.scope, &self.functions)?;
+                if let Some(token) = rest.first() {
+                    return Err(CalculatorError::ExtraTokensDetected { pos: token.pos, len: token.len });
+                }
+                let arity = params.len();
+                self.functions.insert(name.clone(), FunctionDef { params, body });
+                self.history.push(line.to_string());
+                return Ok(format!("{name}({arity} params) defined"));
+            }
+        }
+
+        let contains_equal = tokens.iter().any(|t| t.kind == TokenKind::Equal);
+        let output = if contains_equal {
+            let output = process_expression(line, config)?;
+            if let Ok(roots) = solve_equation(&tokens, config, &Scope::new(), &FunctionRegistry::new()) {
+                if let Some(&last_root) = roots.last() {
+                    self.scope.insert("ans".to_string(), last_root);
+                }
+            }
+            output
+        } else if is_postfix_expression(&tokens) {
+            let value = evaluate_postfix(&tokens)?;
+            self.scope.insert("ans".to_string(), value);
+            format_result(value, config)?
+        } else {
+            let (ast, rest) = parse(&tokens, config, &self.scope, &self.functions)?;
+            if let Some(token) = rest.first() {
+                return Err(CalculatorError::ExtraTokensDetected { pos: token.pos, len: token.len });
+            }
+            let value = evaluate_infix(&ast, &self.scope, &self.functions)?;
+            self.scope.insert("ans".to_string(), value);
+            format_result(value, config)?
+        };
+
+        self.history.push(line.to_string());
+        Ok(output)
+    }
+
+    /// Drives the REPL off `input`, writing a `>` prompt and each line's
+    /// result (or its error, caret-annotated when the error carries a span)
+    /// to `output` until EOF.
+    pub fn run<R: BufRead, W: Write>(&mut self, mut input: R, mut output: W, config: &Config) -> io::Result<()> {
+        let mut line = String::new();
+        loop {
+            write!(output, "> ")?;
+            output.flush()?;
+            line.clear();
+            if input.read_line(&mut line)? == 0 {
+                break;
+            }
+            let trimmed = line.trim();
+            if trimmed.is_empty() {
+                continue;
+            }
+            match self.eval_line(trimmed, config) {
+                Ok(result) => writeln!(output, "{result}")?,
+                Err(err) => writeln!(output, "{}", format_error(trimmed, &err))?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Recognizes `name = expr`: a single bare variable on the left of `=`. Only
+/// the first two tokens are looked at, so any line with a bare name on the
+/// left is an assignment, even one that reads as an equation in that name
+/// (`x = 3 - x` is evaluated, not solved). Equations with the unknown inside
+/// a larger left-hand expression (`2*x+1=3`) and function definitions (whose
+/// head has a `(` right after the name) don't match.
+fn split_assignment(tokens: &[Token]) -> Option<(&String, &[Token])> {
+    match (tokens.first(), tokens.get(1)) {
+        (
+            Some(Token { kind: TokenKind::Variable(name), .. }),
+            Some(Token { kind: TokenKind::Equal, .. }),
+        ) => Some((name, &tokens[2..])),
+        _ => None,
+    }
+}
+
+/// Recognizes a function-definition head `name(p1, p2, ...)`: parsing it the
+/// same way a call expression would parse must yield `AST::Call(name, args)`
+/// with every argument a distinct bare variable, since those are the
+/// parameter names being declared. Anything else (a real equation's LHS, or
+/// a call with non-variable arguments) isn't a definition.
+fn definition_head(tokens: &[Token], config: &Config, scope: &Scope, functions: &FunctionRegistry) -> Option<(String, Vec<String>)> {
+    let (ast, _) = parse(tokens, config, scope, functions).ok()?;
+    let AST::Call(name, args) = ast else { return None };
+
+    let mut params = Vec::with_capacity(args.len());
+    for arg in args {
+        match arg {
+            AST::Var(param) if !params.contains(&param) => params.push(param),
+            _ => return None,
+        }
+    }
+    Some((name, params))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn assignment_binds_a_variable() {
+        let mut repl = Repl::new();
+        assert_eq!(repl.eval_line("a = 3 * 4", &Config::default()), Ok("a=12".to_string()));
+        assert_eq!(repl.binding("a"), Some(12.0));
+    }
+
+    #[test]
+    fn later_expressions_substitute_bound_variables() {
+        let mut repl = Repl::new();
+        repl.eval_line("a = 3 * 4", &Config::default()).unwrap();
+        assert_eq!(repl.eval_line("a + 1", &Config::default()), Ok("13".to_string()));
+    }
+
+    #[test]
+    fn ans_holds_the_previous_result() {
+        let mut repl = Repl::new();
+        repl.eval_line("2 + 2", &Config::default()).unwrap();
+        assert_eq!(repl.eval_line("ans * 10", &Config::default()), Ok("40".to_string()));
+    }
+
+    #[test]
+    fn assignment_can_reference_another_binding() {
+        let mut repl = Repl::new();
+        repl.eval_line("a = 3 * 4", &Config::default()).unwrap();
+        assert_eq!(repl.eval_line("b = a + 1", &Config::default()), Ok("b=13".to_string()));
+    }
+
+    #[test]
+    fn equations_still_solve_without_needing_a_binding() {
+        let mut repl = Repl::new();
+        assert_eq!(repl.eval_line("2 * x + 1 = 3", &Config::default()), Ok("x=1".to_string()));
+    }
+
+    #[test]
+    fn unbound_variable_is_still_an_error() {
+        let mut repl = Repl::new();
+        assert!(repl.eval_line("a + 1", &Config::default()).is_err());
+    }
+
+    #[test]
+    fn history_records_successful_lines_in_order() {
+        let mut repl = Repl::new();
+        repl.eval_line("1 + 1", &Config::default()).unwrap();
+        repl.eval_line("2 + 2", &Config::default()).unwrap();
+        assert_eq!(repl.history(), &["1 + 1".to_string(), "2 + 2".to_string()]);
+    }
+
+    #[test]
+    fn defines_and_calls_a_single_argument_function() {
+        let mut repl = Repl::new();
+        assert_eq!(repl.eval_line("f(x) = x*x + 1", &Config::default()), Ok("f(1 params) defined".to_string()));
+        assert_eq!(repl.arity("f"), Some(1));
+        assert_eq!(repl.eval_line("f(3)", &Config::default()), Ok("10".to_string()));
+    }
+
+    #[test]
+    fn defines_and_calls_a_multi_argument_function() {
+        let mut repl = Repl::new();
+        repl.eval_line("f(x, y) = x * y", &Config::default()).unwrap();
+        assert_eq!(repl.eval_line("f(3, 4)", &Config::default()), Ok("12".to_string()));
+    }
+
+    #[test]
+    fn function_arity_mismatch_is_an_error() {
+        let mut repl = Repl::new();
+        repl.eval_line("f(x, y) = x * y", &Config::default()).unwrap();
+        assert!(repl.eval_line("f(3)", &Config::default()).is_err());
+    }
+
+    #[test]
+    fn unbounded_recursion_is_an_error_not_a_stack_overflow() {
+        let mut repl = Repl::new();
+        repl.eval_line("f(x) = f(x) + 1", &Config::default()).unwrap();
+        assert!(repl.eval_line("f(1)", &Config::default()).is_err());
+    }
+
+    #[test]
+    fn defines_and_calls_a_function_with_a_multi_letter_name() {
+        let mut repl = Repl::new();
+        repl.eval_line("double(x) = x * 2", &Config::default()).unwrap();
+        assert_eq!(repl.eval_line("double(5)", &Config::default()), Ok("10".to_string()));
+    }
+
+    #[test]
+    fn invalid_base_is_an_error_not_a_panic_on_assignment() {
+        let mut repl = Repl::new();
+        let config = Config { base: 40, ..Config::default() };
+        assert_eq!(repl.eval_line("a = 255", &config), Err(CalculatorError::UnknownBase));
+    }
+
+    #[test]
+    fn invalid_base_is_an_error_not_a_panic_on_plain_expression() {
+        let mut repl = Repl::new();
+        let config = Config { base: 40, ..Config::default() };
+        assert_eq!(repl.eval_line("255", &config), Err(CalculatorError::UnknownBase));
+    }
+
+    #[test]
+    fn ans_reflects_the_result_of_solving_an_equation() {
+        let mut repl = Repl::new();
+        repl.eval_line("f(x) = x * x", &Config::default()).unwrap();
+        repl.eval_line("f(3)", &Config::default()).unwrap();
+        repl.eval_line("2 * x + 1 = 3", &Config::default()).unwrap();
+        assert_eq!(repl.eval_line("ans", &Config::default()), Ok("1".to_string()));
+    }
+
+    #[test]
+    fn ans_reflects_the_result_of_a_postfix_expression() {
+        let mut repl = Repl::new();
+        repl.eval_line("f(x) = x * x", &Config::default()).unwrap();
+        repl.eval_line("f(3)", &Config::default()).unwrap();
+        repl.eval_line("3 4 +", &Config::default()).unwrap();
+        assert_eq!(repl.eval_line("ans", &Config::default()), Ok("7".to_string()));
+    }
+}
@@ -0,0 +1,6 @@
+use std::collections::HashMap;
+
+/// Named bindings available while evaluating an expression: REPL variables
+/// assigned with `name = expr`, plus the implicit `ans` binding holding the
+/// previous result.
+pub(crate) type Scope = HashMap<String, f64>;
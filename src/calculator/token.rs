@@ -0,0 +1,25 @@
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind {
+    Number(f64),
+    Variable(String),
+    Function(String),
+    Plus,
+    Minus,
+    Multiply,
+    Divide,
+    Caret,
+    LeftParenthesis,
+    RightParenthesis,
+    Equal,
+    Comma,
+}
+
+/// A lexed token paired with the char span (start offset + length) into the
+/// original input it was read from, so later stages can underline precisely
+/// where a problem occurred.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub pos: usize,
+    pub len: usize,
+}
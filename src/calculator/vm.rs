@@ -0,0 +1,173 @@
+use crate::calculator::ast::{AST, Operator};
+use crate::calculator::calculator::CalculatorError;
+
+/// A single instruction in the flattened, postfix-ordered bytecode produced
+/// by [`compile`]. Operand pushes always precede the operator that consumes
+/// them, so [`Vm::run`] never needs to look ahead.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OpCode {
+    PushNum(f64),
+    PushVar,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+}
+
+/// Compiled bytecode for an [`AST`]. Opaque wrapper so callers can't poke at
+/// the instruction vector directly; it only ever flows into [`Vm::run`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct VmCode(Vec<OpCode>);
+
+/// Lowers an `AST` into postfix bytecode via a post-order traversal: each
+/// `BinOp` emits its operands first, then the operator that combines them.
+/// Compiling once and running many times avoids re-walking the tree on every
+/// evaluation (e.g. sampling `f(x)` over many `x` values). Fails on an
+/// `AST::Call`, since resolving a user-defined function needs a registry the
+/// VM has no way to carry.
+pub fn compile(ast: &AST) -> Result<VmCode, CalculatorError> {
+    let mut code = Vec::new();
+    compile_into(ast, &mut code)?;
+    Ok(VmCode(code))
+}
+
+fn compile_into(ast: &AST, code: &mut Vec<OpCode>) -> Result<(), CalculatorError> {
+    match ast {
+        AST::Num(n) => code.push(OpCode::PushNum(*n)),
+        AST::Var(_) => code.push(OpCode::PushVar),
+        AST::Call(_, _) => return Err(CalculatorError::InvalidExpression),
+        AST::BinOp(lhs, op, rhs) => {
+            compile_into(lhs, code)?;
+            compile_into(rhs, code)?;
+            code.push(match op {
+                Operator::Add => OpCode::Add,
+                Operator::Sub => OpCode::Sub,
+                Operator::Mul => OpCode::Mul,
+                Operator::Div => OpCode::Div,
+                Operator::Pow => OpCode::Pow,
+            });
+        },
+    }
+    Ok(())
+}
+
+/// A minimal stack machine that executes [`VmCode`] against a single
+/// operand stack, mirroring the evaluation semantics of `evaluate_infix`.
+pub struct Vm {
+    arithmetic_stack: Vec<f64>,
+}
+
+impl Default for Vm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Vm {
+    pub fn new() -> Self {
+        Vm { arithmetic_stack: Vec::new() }
+    }
+
+    /// Runs `code` to completion against a fresh stack, binding `var` to any
+    /// `PushVar` instruction. Returns `InvalidExpression` if the final stack
+    /// doesn't hold exactly one value (mirrors the postfix evaluator).
+    pub fn run(&mut self, code: &VmCode, var: Option<f64>) -> Result<f64, CalculatorError> {
+        self.arithmetic_stack.clear();
+
+        for op in &code.0 {
+            match op {
+                OpCode::PushNum(n) => self.arithmetic_stack.push(*n),
+                OpCode::PushVar => {
+                    let value = var.ok_or(CalculatorError::InvalidExpression)?;
+                    self.arithmetic_stack.push(value);
+                },
+                OpCode::Add | OpCode::Sub | OpCode::Mul | OpCode::Div | OpCode::Pow => {
+                    if self.arithmetic_stack.len() < 2 {
+                        return Err(CalculatorError::InvalidExpression);
+                    }
+                    let rhs = self.arithmetic_stack.pop().unwrap();
+                    let lhs = self.arithmetic_stack.pop().unwrap();
+                    let result = match op {
+                        OpCode::Add => lhs + rhs,
+                        OpCode::Sub => lhs - rhs,
+                        OpCode::Mul => lhs * rhs,
+                        OpCode::Div => {
+                            if rhs == 0.0 {
+                                return Err(CalculatorError::DivisionByZero);
+                            }
+                            lhs / rhs
+                        },
+                        OpCode::Pow => lhs.powf(rhs),
+                        _ => unreachable!(),
+                    };
+                    self.arithmetic_stack.push(result);
+                },
+            }
+        }
+
+        if self.arithmetic_stack.len() != 1 {
+            return Err(CalculatorError::InvalidExpression);
+        }
+
+        self.arithmetic_stack.pop().ok_or(CalculatorError::InvalidExpression)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calculator::config::Config;
+    use crate::calculator::evaluator::evaluate_infix;
+    use crate::calculator::functions::FunctionRegistry;
+    use crate::calculator::lexer::lex;
+    use crate::calculator::parser::parse;
+    use crate::calculator::scope::Scope;
+
+    fn ast_for(input: &str) -> AST {
+        let tokens = lex(input);
+        let (ast, _) = parse(&tokens, &Config::default(), &Scope::new(), &FunctionRegistry::new()).unwrap();
+        ast
+    }
+
+    #[test]
+    fn compile_then_run_matches_evaluate_infix() {
+        let ast = ast_for("(3 + 4) * 2^3 - 1");
+        let code = compile(&ast).unwrap();
+        let via_vm = Vm::new().run(&code, None).unwrap();
+        let via_evaluator = evaluate_infix(&ast, &Scope::new(), &FunctionRegistry::new()).unwrap();
+        assert_eq!(via_vm, via_evaluator);
+    }
+
+    #[test]
+    fn compile_then_run_substitutes_a_bound_variable() {
+        let ast = ast_for("x * x + 1");
+        let code = compile(&ast).unwrap();
+        assert_eq!(Vm::new().run(&code, Some(3.0)), Ok(10.0));
+    }
+
+    #[test]
+    fn run_surfaces_division_by_zero() {
+        let ast = ast_for("1 / 0");
+        let code = compile(&ast).unwrap();
+        assert_eq!(Vm::new().run(&code, None), Err(CalculatorError::DivisionByZero));
+    }
+
+    #[test]
+    fn run_rejects_a_stack_underflow() {
+        let code = VmCode(vec![OpCode::PushNum(1.0), OpCode::Add]);
+        assert_eq!(Vm::new().run(&code, None), Err(CalculatorError::InvalidExpression));
+    }
+
+    #[test]
+    fn run_rejects_a_leftover_stack() {
+        let code = VmCode(vec![OpCode::PushNum(1.0), OpCode::PushNum(2.0)]);
+        assert_eq!(Vm::new().run(&code, None), Err(CalculatorError::InvalidExpression));
+    }
+
+    #[test]
+    fn compile_rejects_a_function_call() {
+        let ast = AST::Call("f".to_string(), vec![AST::Num(1.0)]);
+        assert_eq!(compile(&ast), Err(CalculatorError::InvalidExpression));
+    }
+}